@@ -0,0 +1,133 @@
+// Generates the unprefixed opcode dispatch table from instructions.in.
+//
+// Each data row is expanded into every concrete (x,y,z,p,q) combination it
+// covers, the resulting opcode byte is computed, and a `code => builder,`
+// arm (with the builder's `cycles` overridden by the row's cycles column)
+// is emitted into OUT_DIR/decode_base.rs. src/opcode.rs pulls the file
+// in with `include!` and wraps it in the public `decode` function, so the
+// table in instructions.in is the only place opcode/base-cycle info needs
+// to be kept in sync. The mnemonic column is not read here: each opcode's
+// `name` still comes from its builder.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Row {
+    constraints: Vec<(char, usize)>,
+    cycles: u64,
+    builder: String,
+}
+
+fn parse_instructions(src: &str) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for line in src.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split('|').map(|c| c.trim()).collect();
+        if cols.len() != 4 {
+            panic!("instructions.in: malformed row: {}", line);
+        }
+        let constraints = cols[0]
+            .split(';')
+            .map(|term| {
+                let mut parts = term.splitn(2, '=');
+                let var = parts.next().unwrap().trim().chars().next().unwrap();
+                let val: usize = parts.next().unwrap().trim().parse().unwrap();
+                (var, val)
+            })
+            .collect();
+        let cycles: u64 = cols[2].parse()
+            .unwrap_or_else(|_| panic!("instructions.in: bad cycle count: {}", line));
+        rows.push(Row { constraints, cycles, builder: cols[3].to_string() });
+    }
+    rows
+}
+
+fn bound(var: char) -> usize {
+    match var {
+        'x' => 4,
+        'y' => 8,
+        'z' => 8,
+        'p' => 4,
+        'q' => 2,
+        other => panic!("unknown decoding variable {}", other),
+    }
+}
+
+fn fixed(constraints: &[(char, usize)], var: char) -> Option<usize> {
+    constraints.iter().find(|(v, _)| *v == var).map(|(_, val)| *val)
+}
+
+// Replaces the bare `y`, `z`, `p` argument placeholders in a builder call
+// (e.g. "build_ld_r_r(y, z)") with their concrete values for this opcode,
+// leaving identifiers that merely contain those letters (like `build_...`)
+// untouched.
+fn substitute_args(builder: &str, y: usize, z: usize, p: usize) -> String {
+    let mut out = String::new();
+    let bytes = builder.as_bytes();
+    for (i, c) in builder.char_indices() {
+        let is_word_char = |b: u8| (b as char).is_alphanumeric() || b == b'_';
+        let prev_is_word = i > 0 && is_word_char(bytes[i - 1]);
+        let next_is_word = bytes.get(i + 1).map_or(false, |&b| is_word_char(b));
+        if !prev_is_word && !next_is_word && matches!(c, 'y' | 'z' | 'p') {
+            let value = match c { 'y' => y, 'z' => z, 'p' => p, _ => unreachable!() };
+            out.push_str(&value.to_string());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let table_path = Path::new(&manifest_dir).join("instructions.in");
+    println!("cargo:rerun-if-changed={}", table_path.display());
+
+    let table_src = fs::read_to_string(&table_path)
+        .expect("failed to read instructions.in");
+    let rows = parse_instructions(&table_src);
+
+    let mut arms = String::new();
+    let mut seen = std::collections::HashSet::new();
+    for row in &rows {
+        for x in 0..bound('x') {
+            if let Some(fx) = fixed(&row.constraints, 'x') { if fx != x { continue; } }
+            for y in 0..bound('y') {
+                if let Some(fy) = fixed(&row.constraints, 'y') { if fy != y { continue; } }
+                for z in 0..bound('z') {
+                    if let Some(fz) = fixed(&row.constraints, 'z') { if fz != z { continue; } }
+                    let p = y >> 1;
+                    let q = y & 1;
+                    if let Some(fp) = fixed(&row.constraints, 'p') { if fp != p { continue; } }
+                    if let Some(fq) = fixed(&row.constraints, 'q') { if fq != q { continue; } }
+
+                    let code = (x << 6) | (y << 3) | z;
+                    if !seen.insert(code) {
+                        // An earlier, more specific row already claimed this
+                        // opcode (e.g. HALT carving 0x76 out of the LD r,r
+                        // block) — first match in instructions.in wins.
+                        continue;
+                    }
+                    let builder = substitute_args(&row.builder, y, z, p);
+                    arms.push_str(&format!(
+                        "        {} => {{ let mut op = {}; op.cycles = {}; op }},\n",
+                        code, builder, row.cycles));
+                }
+            }
+        }
+    }
+
+    let generated = format!(
+        "// @generated by build.rs from instructions.in. Do not edit by hand.\n\
+         match code {{\n{}        _ => build_illegal(&[code]),\n    }}\n",
+        arms
+    );
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("decode_base.rs"), generated)
+        .expect("failed to write decode_base.rs");
+}
@@ -0,0 +1,307 @@
+//! Standalone disassembler, gated behind the `disasm` feature so minimal
+//! builds don't pay for it.
+//!
+//! This walks a region of memory byte by byte, following the CB/ED/DD/FD
+//! prefix bytes, and renders one line per instruction: address, raw byte
+//! sequence, and the mnemonic with operands resolved (`IX+d`/`IY+d`
+//! displacements, immediate `n`/`nn` values, and relative jump targets
+//! computed as `pc + 2 + offset`). It decodes independently of
+//! opcode.rs/opcode_arith.rs/opcode_bits.rs, since it needs to describe
+//! opcodes the execution engine doesn't implement yet.
+
+#![cfg(feature = "disasm")]
+
+use super::state::State;
+
+const TABLE_R: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const TABLE_RP: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const TABLE_RP2: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const TABLE_CC: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+const TABLE_ALU: [&str; 8] = ["ADD A,", "ADC A,", "SUB", "SBC A,", "AND", "XOR", "OR", "CP"];
+const TABLE_ROT: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SRL"];
+const TABLE_IM: [&str; 8] = ["0", "0", "1", "2", "0", "0", "1", "2"];
+const TABLE_BLOCK: [[&str; 4]; 4] = [
+    ["LDI", "LDD", "LDIR", "LDDR"],
+    ["CPI", "CPD", "CPIR", "CPDR"],
+    ["INI", "IND", "INIR", "INDR"],
+    ["OUTI", "OUTD", "OTIR", "OTDR"],
+];
+
+/// One decoded line: address, raw bytes as fetched, and the mnemonic.
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let hex: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{:04X}  {:<12} {}", self.address, hex.join(" "), self.mnemonic)
+    }
+}
+
+// Tracks the bytes fetched for the instruction currently being decoded,
+// and the displacement byte read for a DD/FD-prefixed (IX+d)/(IY+d)
+// operand, if any was needed.
+struct Cursor<'a> {
+    state: &'a State,
+    address: u16,
+    bytes: Vec<u8>,
+    displacement: Option<i8>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(state: &'a State, address: u16) -> Self {
+        Cursor {state, address, bytes: Vec::new(), displacement: None}
+    }
+
+    fn peek(&self) -> u8 {
+        self.state.mem.peek(self.address)
+    }
+
+    fn next(&mut self) -> u8 {
+        let value = self.peek();
+        self.bytes.push(value);
+        self.address = self.address.wrapping_add(1);
+        value
+    }
+
+    fn imm8(&mut self) -> u8 {
+        self.next()
+    }
+
+    fn imm16(&mut self) -> u16 {
+        let lo = self.next() as u16;
+        let hi = self.next() as u16;
+        (hi << 8) | lo
+    }
+
+    // Target is relative to the address right after the instruction:
+    // by the time the offset byte is consumed, `self.address` already
+    // sits at pc + 2.
+    fn relative_target(&mut self) -> u16 {
+        let offset = self.next() as i8 as i16;
+        self.address.wrapping_add(offset as u16)
+    }
+
+    fn displacement(&mut self) -> i8 {
+        if let Some(d) = self.displacement {
+            return d;
+        }
+        let d = self.next() as i8;
+        self.displacement = Some(d);
+        d
+    }
+}
+
+pub struct Disassembler<'a> {
+    state: &'a State,
+}
+
+impl<'a> Disassembler<'a> {
+    pub fn new(state: &'a State) -> Self {
+        Disassembler {state}
+    }
+
+    pub fn disassemble(&self, address: u16) -> Instruction {
+        let mut cursor = Cursor::new(self.state, address);
+        let mnemonic = self.decode(&mut cursor, "HL");
+        Instruction {address, bytes: cursor.bytes, mnemonic}
+    }
+
+    // Disassembles `count` instructions starting at `start`, one after
+    // another in memory order.
+    pub fn list(&self, start: u16, count: usize) -> Vec<Instruction> {
+        let mut address = start;
+        let mut lines = Vec::with_capacity(count);
+        for _ in 0..count {
+            let instr = self.disassemble(address);
+            address = address.wrapping_add(instr.bytes.len().max(1) as u16);
+            lines.push(instr);
+        }
+        lines
+    }
+
+    fn decode(&self, cursor: &mut Cursor, index_reg: &'static str) -> String {
+        let code = cursor.next();
+        match code {
+            0xCB => self.decode_cb(cursor),
+            0xED => self.decode_ed(cursor),
+            0xDD => self.decode_indexed(cursor, "IX"),
+            0xFD => self.decode_indexed(cursor, "IY"),
+            _ => self.decode_base(cursor, code, index_reg),
+        }
+    }
+
+    // DD/FD followed by CB uses a different byte order than a plain
+    // DD/FD opcode: prefix, CB, displacement, then the bit-op opcode.
+    fn decode_indexed(&self, cursor: &mut Cursor, index_reg: &'static str) -> String {
+        if cursor.peek() == 0xCB {
+            cursor.next(); // consume CB
+            let d = cursor.next() as i8;
+            let op = cursor.next();
+            self.decode_cb_op(op, Some((index_reg, d)))
+        } else {
+            self.decode(cursor, index_reg)
+        }
+    }
+
+    // `r[idx]` for the base table, resolved against whichever index
+    // register (HL, IX or IY) is active. (HL) becomes `(IX+d)`/`(IY+d)`,
+    // consuming a displacement byte the first time it's needed; H/L
+    // become the undocumented IXH/IXL, IYH/IYL forms.
+    fn reg_name(&self, cursor: &mut Cursor, idx: usize, index_reg: &str) -> String {
+        if index_reg == "HL" {
+            return TABLE_R[idx].to_string();
+        }
+        match idx {
+            6 => {
+                let d = cursor.displacement();
+                format!("({}{:+})", index_reg, d)
+            },
+            4 => format!("{}H", index_reg),
+            5 => format!("{}L", index_reg),
+            _ => TABLE_R[idx].to_string(),
+        }
+    }
+
+    fn decode_base(&self, cursor: &mut Cursor, code: u8, index_reg: &'static str) -> String {
+        let x = (code >> 6) as usize;
+        let y = ((code >> 3) & 7) as usize;
+        let z = (code & 7) as usize;
+        let p = y >> 1;
+        let q = y & 1;
+        let rp = |p: usize| if p == 2 {index_reg.to_string()} else {TABLE_RP[p].to_string()};
+        let rp2 = |p: usize| if p == 2 {index_reg.to_string()} else {TABLE_RP2[p].to_string()};
+
+        match (x, z) {
+            (0, 0) => match y {
+                0 => "NOP".to_string(),
+                1 => "EX AF, AF'".to_string(),
+                2 => format!("DJNZ {:04X}", cursor.relative_target()),
+                3 => format!("JR {:04X}", cursor.relative_target()),
+                _ => format!("JR {}, {:04X}", TABLE_CC[y - 4], cursor.relative_target()),
+            },
+            (0, 1) => if q == 0 {
+                format!("LD {}, {:04X}", rp(p), cursor.imm16())
+            } else {
+                format!("ADD {}, {}", index_reg, rp(p))
+            },
+            (0, 2) => match (q, p) {
+                (0, 0) => "LD (BC), A".to_string(),
+                (0, 1) => "LD (DE), A".to_string(),
+                (0, 2) => format!("LD ({:04X}), {}", cursor.imm16(), index_reg),
+                (0, 3) => format!("LD ({:04X}), A", cursor.imm16()),
+                (1, 0) => "LD A, (BC)".to_string(),
+                (1, 1) => "LD A, (DE)".to_string(),
+                (1, 2) => format!("LD {}, ({:04X})", index_reg, cursor.imm16()),
+                _ => format!("LD A, ({:04X})", cursor.imm16()),
+            },
+            (0, 3) => format!("{} {}", if q == 0 {"INC"} else {"DEC"}, rp(p)),
+            (0, 4) => format!("INC {}", self.reg_name(cursor, y, index_reg)),
+            (0, 5) => format!("DEC {}", self.reg_name(cursor, y, index_reg)),
+            (0, 6) => {
+                let dst = self.reg_name(cursor, y, index_reg);
+                format!("LD {}, {:02X}", dst, cursor.imm8())
+            },
+            (0, 7) => ["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"][y].to_string(),
+            (1, 6) if y == 6 => "HALT".to_string(),
+            (1, _) => format!(
+                "LD {}, {}",
+                self.reg_name(cursor, y, index_reg),
+                self.reg_name(cursor, z, index_reg)
+            ),
+            (2, _) => format!("{} {}", TABLE_ALU[y], self.reg_name(cursor, z, index_reg)),
+            (3, 0) => format!("RET {}", TABLE_CC[y]),
+            (3, 1) => if q == 0 {
+                format!("POP {}", rp2(p))
+            } else {
+                match p {
+                    0 => "RET".to_string(),
+                    1 => "EXX".to_string(),
+                    2 => format!("JP ({})", index_reg),
+                    _ => format!("LD SP, {}", index_reg),
+                }
+            },
+            (3, 2) => format!("JP {}, {:04X}", TABLE_CC[y], cursor.imm16()),
+            (3, 3) => match y {
+                0 => format!("JP {:04X}", cursor.imm16()),
+                1 => self.decode_cb(cursor),
+                2 => format!("OUT ({:02X}), A", cursor.imm8()),
+                3 => format!("IN A, ({:02X})", cursor.imm8()),
+                4 => format!("EX (SP), {}", index_reg),
+                5 => "EX DE, HL".to_string(),
+                6 => "DI".to_string(),
+                _ => "EI".to_string(),
+            },
+            (3, 4) => format!("CALL {}, {:04X}", TABLE_CC[y], cursor.imm16()),
+            (3, 5) => if q == 0 {
+                format!("PUSH {}", rp2(p))
+            } else if p == 0 {
+                format!("CALL {:04X}", cursor.imm16())
+            } else {
+                // p=1/2/3 (DD/ED/FD) never reach here: decode() intercepts
+                // those bytes before calling decode_base.
+                "??".to_string()
+            },
+            (3, 6) => format!("{} {:02X}", TABLE_ALU[y], cursor.imm8()),
+            (3, 7) => format!("RST {:02X}", y * 8),
+            _ => "??".to_string(),
+        }
+    }
+
+    fn decode_cb(&self, cursor: &mut Cursor) -> String {
+        let op = cursor.next();
+        self.decode_cb_op(op, None)
+    }
+
+    // `displaced` carries the index register name and resolved
+    // displacement for the DD/FD CB d op form; None means a plain,
+    // unprefixed CB op acting directly on r[z].
+    fn decode_cb_op(&self, op: u8, displaced: Option<(&str, i8)>) -> String {
+        let x = (op >> 6) as usize;
+        let y = ((op >> 3) & 7) as usize;
+        let z = (op & 7) as usize;
+        let operand = match displaced {
+            Some((index_reg, d)) => format!("({}{:+})", index_reg, d),
+            None => TABLE_R[z].to_string(),
+        };
+        match x {
+            0 => format!("{} {}", TABLE_ROT[y], operand),
+            1 => format!("BIT {}, {}", y, operand),
+            2 => format!("RES {}, {}", y, operand),
+            _ => format!("SET {}, {}", y, operand),
+        }
+    }
+
+    fn decode_ed(&self, cursor: &mut Cursor) -> String {
+        let code = cursor.next();
+        let x = (code >> 6) as usize;
+        let y = ((code >> 3) & 7) as usize;
+        let z = (code & 7) as usize;
+        let p = y >> 1;
+        let q = y & 1;
+
+        match (x, z) {
+            (1, 0) => if y == 6 {"IN (C)".to_string()} else {format!("IN {}, (C)", TABLE_R[y])},
+            (1, 1) => if y == 6 {"OUT (C), 0".to_string()} else {format!("OUT (C), {}", TABLE_R[y])},
+            (1, 2) => format!(
+                "{} HL, {}",
+                if q == 0 {"SBC"} else {"ADC"},
+                TABLE_RP[p]
+            ),
+            (1, 3) => if q == 0 {
+                format!("LD ({:04X}), {}", cursor.imm16(), TABLE_RP[p])
+            } else {
+                format!("LD {}, ({:04X})", TABLE_RP[p], cursor.imm16())
+            },
+            (1, 4) => "NEG".to_string(),
+            (1, 5) => if y == 1 {"RETI".to_string()} else {"RETN".to_string()},
+            (1, 6) => format!("IM {}", TABLE_IM[y]),
+            (1, 7) => ["LD I, A", "LD R, A", "LD A, I", "LD A, R", "RRD", "RLD", "NOP", "NOP"][y].to_string(),
+            (2, 0..=3) if y >= 4 => TABLE_BLOCK[z][y - 4].to_string(),
+            _ => "??".to_string(),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Z80 maskable and non-maskable interrupt handling: the IFF1/IFF2
+//! flip-flops, the interrupt mode register, and the one-instruction
+//! delay between `EI` executing and interrupts actually being accepted.
+//!
+//! `Interrupts` holds the flip-flop/mode state and is meant to live on
+//! `State` (`state.interrupts`); the free functions here act on
+//! `Environment` the same way the `operator_*` functions in operators.rs
+//! do, so host code and devices can raise an interrupt without reaching
+//! into CPU internals.
+
+use super::environment::*;
+use super::registers::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InterruptMode {
+    Im0,
+    Im1,
+    Im2,
+}
+
+pub struct Interrupts {
+    pub iff1: bool,
+    pub iff2: bool,
+    pub mode: InterruptMode,
+    // Counts down after the last EI: 2 when EI executes, 1 once EI itself
+    // has completed (`end_instruction`), 0 once the instruction following
+    // it has completed and interrupts may actually be accepted. This gives
+    // EI/RET the required one instruction of immunity.
+    ei_delay: u8,
+    pending_maskable: Option<u8>,
+    pending_nmi: bool,
+}
+
+impl Interrupts {
+    pub fn new() -> Self {
+        Interrupts {
+            iff1: false,
+            iff2: false,
+            mode: InterruptMode::Im0,
+            ei_delay: 0,
+            pending_maskable: None,
+            pending_nmi: false,
+        }
+    }
+
+    /// `DI`: both flip-flops drop immediately.
+    pub fn di(&mut self) {
+        self.iff1 = false;
+        self.iff2 = false;
+        self.ei_delay = 0;
+    }
+
+    /// `EI`: both flip-flops are set, but acceptance is deferred until
+    /// after the instruction following this one has executed.
+    pub fn ei(&mut self) {
+        self.iff1 = true;
+        self.iff2 = true;
+        self.ei_delay = 2;
+    }
+
+    pub fn set_mode(&mut self, mode: InterruptMode) {
+        self.mode = mode;
+    }
+
+    /// Raises a maskable interrupt with the given bus vector (the value
+    /// IM0 executes, or the low byte of the IM2 pointer). Acceptance
+    /// happens the next time `service_pending` runs and `IFF1` allows it.
+    pub fn raise(&mut self, bus_vector: u8) {
+        self.pending_maskable = Some(bus_vector);
+    }
+
+    /// Raises an NMI. NMIs are always serviced, regardless of IFF1.
+    pub fn raise_nmi(&mut self) {
+        self.pending_nmi = true;
+    }
+
+    /// Called once after every instruction executes.
+    pub fn end_instruction(&mut self) {
+        if self.ei_delay > 0 {
+            self.ei_delay -= 1;
+        }
+    }
+
+    fn can_accept_maskable(&self) -> bool {
+        self.iff1 && self.ei_delay == 0
+    }
+}
+
+/// Services a pending NMI or maskable interrupt, if any. Intended to be
+/// called by the host's run loop between instructions, after the
+/// opcode's own `execute` and `Interrupts::end_instruction`.
+pub fn service_pending(env: &mut Environment) {
+    if env.state.interrupts.pending_nmi {
+        env.state.interrupts.pending_nmi = false;
+        accept_nmi(env);
+        return;
+    }
+
+    if env.state.interrupts.can_accept_maskable() {
+        if let Some(bus_vector) = env.state.interrupts.pending_maskable.take() {
+            accept_maskable(env, bus_vector);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ei_delays_acceptance_for_one_instruction() {
+        let mut interrupts = Interrupts::new();
+        interrupts.ei();
+        // EI itself has not completed yet.
+        assert!(!interrupts.can_accept_maskable());
+
+        interrupts.end_instruction();
+        // EI has completed; the instruction that follows it must still
+        // run uninterrupted.
+        assert!(!interrupts.can_accept_maskable());
+
+        interrupts.end_instruction();
+        // The instruction following EI has completed: the delay window is over.
+        assert!(interrupts.can_accept_maskable());
+    }
+
+    #[test]
+    fn di_cancels_a_pending_ei_delay() {
+        let mut interrupts = Interrupts::new();
+        interrupts.ei();
+        interrupts.di();
+        interrupts.end_instruction();
+        interrupts.end_instruction();
+        assert!(!interrupts.can_accept_maskable());
+    }
+
+    #[test]
+    fn without_ei_interrupts_stay_disabled() {
+        let mut interrupts = Interrupts::new();
+        interrupts.end_instruction();
+        assert!(!interrupts.can_accept_maskable());
+    }
+}
+
+fn accept_nmi(env: &mut Environment) {
+    env.state.interrupts.iff2 = env.state.interrupts.iff1;
+    env.state.interrupts.iff1 = false;
+    let pc = env.get_pc();
+    env.push16(pc);
+    env.set_pc(0x0066);
+    env.state.cycles += 11;
+}
+
+fn accept_maskable(env: &mut Environment, bus_vector: u8) {
+    env.state.interrupts.iff1 = false;
+    env.state.interrupts.iff2 = false;
+    let pc = env.get_pc();
+    env.push16(pc);
+
+    match env.state.interrupts.mode {
+        InterruptMode::Im0 => {
+            // The bus typically supplies an RST opcode; just execute it.
+            // Its own `cycles` are already added by `execute`, so only
+            // the acknowledge overhead goes on top here.
+            super::opcode::decode(bus_vector).execute(env);
+            env.state.cycles += 2;
+        },
+        InterruptMode::Im1 => {
+            env.set_pc(0x0038);
+            env.state.cycles += 13;
+        },
+        InterruptMode::Im2 => {
+            let i = env.state.reg.get8(Reg8::I);
+            let vector = ((i as u16) << 8) | bus_vector as u16;
+            let lo = env.state.mem.peek(vector) as u16;
+            let hi = env.state.mem.peek(vector.wrapping_add(1)) as u16;
+            env.set_pc((hi << 8) | lo);
+            env.state.cycles += 19;
+        },
+    }
+}
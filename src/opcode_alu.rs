@@ -0,0 +1,52 @@
+use super::opcode::*;
+use super::environment::*;
+use super::operators::*;
+use super::registers::*;
+
+const TABLE_ALU_NAME: [&str; 8] = ["ADD A,", "ADC A,", "SUB", "SBC A,", "AND", "XOR", "OR", "CP"];
+
+fn apply(op: usize, env: &mut Environment, a: u8, b: u8) -> u8 {
+    match op {
+        0 => operator_add(env, a, b),
+        1 => operator_adc(env, a, b),
+        2 => operator_sub(env, a, b),
+        3 => operator_sbc(env, a, b),
+        4 => operator_and(env, a, b),
+        5 => operator_xor(env, a, b),
+        6 => operator_or(env, a, b),
+        _ => operator_cp(env, a, b),
+    }
+}
+
+// x=2: the 8-bit ALU group. `op` is the y bits (0=ADD .. 7=CP), already
+// the same index TABLE_ALU_NAME and `apply` use.
+pub fn build_alu_r(op: usize, r: Reg8) -> Opcode {
+    Opcode {
+        name: format!("{} {:?}", TABLE_ALU_NAME[op], r),
+        cycles: 4,
+        action: Box::new(move |env: &mut Environment| {
+            let a = env.state.reg.get_a();
+            let b = env.get_reg(r);
+            let v = apply(op, env, a, b);
+            if op != 7 {
+                // CP sets flags like SUB but discards the result.
+                env.state.reg.set_a(v);
+            }
+        })
+    }
+}
+
+pub fn build_alu_n(op: usize) -> Opcode {
+    Opcode {
+        name: format!("{} n", TABLE_ALU_NAME[op]),
+        cycles: 7,
+        action: Box::new(move |env: &mut Environment| {
+            let a = env.state.reg.get_a();
+            let b = env.advance_pc();
+            let v = apply(op, env, a, b);
+            if op != 7 {
+                env.state.reg.set_a(v);
+            }
+        })
+    }
+}
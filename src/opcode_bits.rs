@@ -1,5 +1,5 @@
 use super::opcode::*;
-use super::state::*;
+use super::environment::*;
 use super::registers::*;
 
 #[derive(Copy, Clone)]
@@ -16,13 +16,27 @@ pub enum ShiftDir {
     Right
 }
 
+// The eight CB-page rotate/shift ops in y order, shared with the x=0,z=7
+// accumulator rotates (RLCA/RRCA/RLA/RRA) which are the same operations
+// run on A with fast=true.
+pub const TABLE_ROT_OPS: [(ShiftDir, ShiftMode, &str); 8] = [
+    (ShiftDir::Left, ShiftMode::RotateCarry, "RLC"),
+    (ShiftDir::Right, ShiftMode::RotateCarry, "RRC"),
+    (ShiftDir::Left, ShiftMode::Rotate, "RL"),
+    (ShiftDir::Right, ShiftMode::Rotate, "RR"),
+    (ShiftDir::Left, ShiftMode::Arithmetic, "SLA"),
+    (ShiftDir::Right, ShiftMode::Arithmetic, "SRA"),
+    (ShiftDir::Left, ShiftMode::Logical, "SLL"),
+    (ShiftDir::Right, ShiftMode::Logical, "SRL"),
+];
+
 pub fn build_rot_r(r: Reg8, (dir, mode, name): (ShiftDir, ShiftMode, &str), fast: bool) -> Opcode {
     let separator = if fast {""} else {" "};
     Opcode {
         name: format!("{}{}{}", name, separator, r),
         cycles: if fast {4} else {8},
-        action: Box::new(move |state: &mut State| {
-            let mut v = state.get_reg(r);
+        action: Box::new(move |env: &mut Environment| {
+            let mut v = env.get_reg(r);
             let carry: bool;
 
             match dir {
@@ -32,7 +46,7 @@ pub fn build_rot_r(r: Reg8, (dir, mode, name): (ShiftDir, ShiftMode, &str), fast
                     let set_lower_bit = match mode {
                         ShiftMode::Arithmetic => false, // always 0 in bit 0
                         ShiftMode::Logical => true, // always 1 in bit 0
-                        ShiftMode::Rotate => state.reg.get_flag(Flag::C), // carry in bit 0
+                        ShiftMode::Rotate => env.state.reg.get_flag(Flag::C), // carry in bit 0
                         ShiftMode::RotateCarry => upper_bit, // bit 7 moves to bit 0
                     };
                     if set_lower_bit { // bit 0 is 0 already
@@ -47,7 +61,7 @@ pub fn build_rot_r(r: Reg8, (dir, mode, name): (ShiftDir, ShiftMode, &str), fast
                     let set_upper_bit = match mode {
                         ShiftMode::Arithmetic => upper_bit, // extend bit 7
                         ShiftMode::Logical => false, // always 0 in bit 7
-                        ShiftMode::Rotate => state.reg.get_flag(Flag::C), // carry in bit 0
+                        ShiftMode::Rotate => env.state.reg.get_flag(Flag::C), // carry in bit 7
                         ShiftMode::RotateCarry => lower_bit, // bit 0 goes to bit 7
                     };
                     if set_upper_bit { // bit 7 is 0 already
@@ -56,13 +70,13 @@ pub fn build_rot_r(r: Reg8, (dir, mode, name): (ShiftDir, ShiftMode, &str), fast
                     carry = lower_bit;
                 }
             }
-            state.set_reg(r, v);
-            state.reg.put_flag(Flag::C, carry);
-            state.reg.clear_flag(Flag::H);
-            state.reg.clear_flag(Flag::N);
+            env.set_reg(r, v);
+            env.state.reg.put_flag(Flag::C, carry);
+            env.state.reg.clear_flag(Flag::H);
+            env.state.reg.clear_flag(Flag::N);
             if !fast {
-                state.reg.update_sz53_flags(v);
-                state.reg.update_p_flag(v);
+                env.state.reg.update_sz53_flags(v);
+                env.state.reg.update_p_flag(v);
             }
         })
     }
@@ -72,10 +86,10 @@ pub fn build_bit_r(bit: u8, r: Reg8) -> Opcode {
     Opcode {
         name: format!("BIT {}, {}", bit, r),
         cycles: 8, // (HL) 8, (IX+d) 20
-        action: Box::new(move |state: &mut State| {
-            let v8 = state.get_reg(r);
+        action: Box::new(move |env: &mut Environment| {
+            let v8 = env.get_reg(r);
             let v1 = (v8 & (1<<bit)) != 0;
-            state.reg.put_flag(Flag::Z, v1);
+            env.state.reg.put_flag(Flag::Z, v1);
         })
     }
 }
@@ -84,10 +98,10 @@ pub fn build_set_r(bit: u8, r: Reg8) -> Opcode {
     Opcode {
         name: format!("SET {}, {}", bit, r),
         cycles: 8, // (HL) 15, (IX+d) 23
-        action: Box::new(move |state: &mut State| {
-            let mut v = state.get_reg(r);
+        action: Box::new(move |env: &mut Environment| {
+            let mut v = env.get_reg(r);
             v = v | (1<<bit);
-            state.set_reg(r, v);
+            env.set_reg(r, v);
         })
     }
 }
@@ -96,10 +110,10 @@ pub fn build_res_r(bit: u8, r: Reg8) -> Opcode {
     Opcode {
         name: format!("RES {}, {}", bit, r),
         cycles: 8, // (HL) 15, (IX+d) 23
-        action: Box::new(move |state: &mut State| {
-            let mut v = state.get_reg(r);
+        action: Box::new(move |env: &mut Environment| {
+            let mut v = env.get_reg(r);
             v = v & !(1<<bit);
-            state.set_reg(r, v);
+            env.set_reg(r, v);
         })
     }
 }
@@ -108,13 +122,13 @@ pub fn build_cpl() -> Opcode {
     Opcode {
         name: "CPL".to_string(),
         cycles: 4,
-        action: Box::new(move |state: &mut State| {
-            let mut v = state.reg.get8(Reg8::A);
+        action: Box::new(move |env: &mut Environment| {
+            let mut v = env.state.reg.get8(Reg8::A);
             v = !v;
-            state.reg.set8(Reg8::A, v); 
+            env.state.reg.set8(Reg8::A, v);
 
-            state.reg.set_flag(Flag::H);
-            state.reg.set_flag(Flag::N);
+            env.state.reg.set_flag(Flag::H);
+            env.state.reg.set_flag(Flag::N);
         })
     }
 }
@@ -123,22 +137,22 @@ pub fn build_scf() -> Opcode {
     Opcode {
         name: "SCF".to_string(),
         cycles: 4,
-        action: Box::new(move |state: &mut State| {
-            state.reg.set_flag(Flag::C);
-            state.reg.clear_flag(Flag::H);
-            state.reg.clear_flag(Flag::N);
+        action: Box::new(move |env: &mut Environment| {
+            env.state.reg.set_flag(Flag::C);
+            env.state.reg.clear_flag(Flag::H);
+            env.state.reg.clear_flag(Flag::N);
         })
     }
 }
 
 pub fn build_ccf() -> Opcode {
     Opcode {
-        name: "SCF".to_string(),
+        name: "CCF".to_string(),
         cycles: 4,
-        action: Box::new(move |state: &mut State| {
-            state.reg.put_flag(Flag::C, !state.reg.get_flag(Flag::C));
-            state.reg.put_flag(Flag::H, !state.reg.get_flag(Flag::H));
-            state.reg.clear_flag(Flag::N);
+        action: Box::new(move |env: &mut Environment| {
+            env.state.reg.put_flag(Flag::C, !env.state.reg.get_flag(Flag::C));
+            env.state.reg.put_flag(Flag::H, !env.state.reg.get_flag(Flag::H));
+            env.state.reg.clear_flag(Flag::N);
         })
     }
 }
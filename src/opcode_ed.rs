@@ -0,0 +1,200 @@
+//! Builders specific to the ED prefix page: the block transfer/search/IO
+//! instructions (LDI/LDD/LDIR/LDDR, CPI/CPD/CPIR/CPDR, INI/IND/INIR/INDR,
+//! OUTI/OUTD/OTIR/OTDR) and the I/R register loads. 16-bit ADD/ADC/SBC,
+//! NEG and IM/RETN/RETI live in opcode_arith.rs/opcode_interrupt.rs and
+//! are reused as-is from decode_ed in opcode.rs.
+
+use super::opcode::*;
+use super::environment::*;
+use super::registers::*;
+
+pub fn build_ed_ld_inn_rr(rr: Reg16) -> Opcode {
+    Opcode {
+        name: format!("LD (nn), {:?}", rr),
+        cycles: 20,
+        action: Box::new(move |env: &mut Environment| {
+            let addr = env.advance_immediate16();
+            let v = env.get_reg16(rr);
+            env.state.mem.poke(addr, (v & 0xFF) as u8);
+            env.state.mem.poke(addr.wrapping_add(1), (v >> 8) as u8);
+        })
+    }
+}
+
+pub fn build_ed_ld_rr_inn(rr: Reg16) -> Opcode {
+    Opcode {
+        name: format!("LD {:?}, (nn)", rr),
+        cycles: 20,
+        action: Box::new(move |env: &mut Environment| {
+            let addr = env.advance_immediate16();
+            let lo = env.state.mem.peek(addr) as u16;
+            let hi = env.state.mem.peek(addr.wrapping_add(1)) as u16;
+            env.set_reg16(rr, (hi << 8) | lo);
+        })
+    }
+}
+
+pub fn build_ld_i_a() -> Opcode {
+    Opcode {
+        name: "LD I, A".to_string(),
+        cycles: 9,
+        action: Box::new(|env: &mut Environment| {
+            let a = env.state.reg.get_a();
+            env.state.reg.set8(Reg8::I, a);
+        })
+    }
+}
+
+pub fn build_ld_r_a() -> Opcode {
+    Opcode {
+        name: "LD R, A".to_string(),
+        cycles: 9,
+        action: Box::new(|env: &mut Environment| {
+            let a = env.state.reg.get_a();
+            env.state.reg.set8(Reg8::R, a);
+        })
+    }
+}
+
+pub fn build_ld_a_i() -> Opcode {
+    Opcode {
+        name: "LD A, I".to_string(),
+        cycles: 9,
+        action: Box::new(|env: &mut Environment| {
+            let i = env.state.reg.get8(Reg8::I);
+            env.state.reg.set_a(i);
+            env.state.reg.update_sz53_flags(i);
+            env.state.reg.put_flag(Flag::P, env.state.interrupts.iff2);
+            env.state.reg.clear_flag(Flag::H);
+            env.state.reg.clear_flag(Flag::N);
+        })
+    }
+}
+
+pub fn build_ld_a_r() -> Opcode {
+    Opcode {
+        name: "LD A, R".to_string(),
+        cycles: 9,
+        action: Box::new(|env: &mut Environment| {
+            let r = env.state.reg.get8(Reg8::R);
+            env.state.reg.set_a(r);
+            env.state.reg.update_sz53_flags(r);
+            env.state.reg.put_flag(Flag::P, env.state.interrupts.iff2);
+            env.state.reg.clear_flag(Flag::H);
+            env.state.reg.clear_flag(Flag::N);
+        })
+    }
+}
+
+// `dir` is +1 for the "increment" forms (LDI/CPI/INI/OUTI) and -1 for the
+// "decrement" forms (LDD/CPD/IND/OUTD); `repeat` selects the *R variant,
+// which re-runs the instruction (by backing PC up 2 bytes) until BC hits
+// zero (LDIR/LDDR) or B hits zero (INIR/INDR/OTIR/OTDR).
+pub fn build_block_ld(dir: i16, repeat: bool) -> Opcode {
+    Opcode {
+        name: format!("LD{}{}", if dir > 0 {"I"} else {"D"}, if repeat {"R"} else {""}),
+        cycles: if repeat {21} else {16},
+        action: Box::new(move |env: &mut Environment| {
+            let hl = env.state.reg.get16(Reg16::HL);
+            let de = env.state.reg.get16(Reg16::DE);
+            let bc = env.state.reg.get16(Reg16::BC).wrapping_sub(1);
+            let v = env.state.mem.peek(hl);
+            env.state.mem.poke(de, v);
+            env.state.reg.set16(Reg16::HL, hl.wrapping_add(dir as u16));
+            env.state.reg.set16(Reg16::DE, de.wrapping_add(dir as u16));
+            env.state.reg.set16(Reg16::BC, bc);
+            env.state.reg.clear_flag(Flag::H);
+            env.state.reg.clear_flag(Flag::N);
+            env.state.reg.put_flag(Flag::P, bc != 0);
+            if repeat && bc != 0 {
+                let pc = env.get_pc();
+                env.set_pc(pc.wrapping_sub(2));
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+pub fn build_block_cp(dir: i16, repeat: bool) -> Opcode {
+    Opcode {
+        name: format!("CP{}{}", if dir > 0 {"I"} else {"D"}, if repeat {"R"} else {""}),
+        cycles: if repeat {21} else {16},
+        action: Box::new(move |env: &mut Environment| {
+            let hl = env.state.reg.get16(Reg16::HL);
+            let a = env.state.reg.get_a();
+            let v = env.state.mem.peek(hl);
+            let bc = env.state.reg.get16(Reg16::BC).wrapping_sub(1);
+            env.state.reg.set16(Reg16::HL, hl.wrapping_add(dir as u16));
+            env.state.reg.set16(Reg16::BC, bc);
+            let result = a.wrapping_sub(v);
+            env.state.reg.put_flag(Flag::Z, result == 0);
+            env.state.reg.put_flag(Flag::S, (result & 0x80) != 0);
+            env.state.reg.set_flag(Flag::N);
+            env.state.reg.put_flag(Flag::P, bc != 0);
+            if repeat && bc != 0 && result != 0 {
+                let pc = env.get_pc();
+                env.set_pc(pc.wrapping_sub(2));
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+pub fn build_block_in(dir: i16, repeat: bool) -> Opcode {
+    Opcode {
+        name: format!("IN{}{}", if dir > 0 {"I"} else {"D"}, if repeat {"R"} else {""}),
+        cycles: if repeat {21} else {16},
+        action: Box::new(move |env: &mut Environment| {
+            let hl = env.state.reg.get16(Reg16::HL);
+            let bc = env.state.reg.get16(Reg16::BC);
+            let value = env.port_in(bc);
+            env.state.mem.poke(hl, value);
+            env.state.reg.set16(Reg16::HL, hl.wrapping_add(dir as u16));
+            let b = env.state.reg.get8(Reg8::B).wrapping_sub(1);
+            env.state.reg.set8(Reg8::B, b);
+            env.state.reg.put_flag(Flag::Z, b == 0);
+            env.state.reg.set_flag(Flag::N);
+            if repeat && b != 0 {
+                let pc = env.get_pc();
+                env.set_pc(pc.wrapping_sub(2));
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+pub fn build_block_out(dir: i16, repeat: bool) -> Opcode {
+    Opcode {
+        name: format!("{}{}{}", if repeat {"OT"} else {"OUT"}, if dir > 0 {"I"} else {"D"}, if repeat {"R"} else {""}),
+        cycles: if repeat {21} else {16},
+        action: Box::new(move |env: &mut Environment| {
+            let hl = env.state.reg.get16(Reg16::HL);
+            let value = env.state.mem.peek(hl);
+            let bc = env.state.reg.get16(Reg16::BC);
+            env.port_out(bc, value);
+            env.state.reg.set16(Reg16::HL, hl.wrapping_add(dir as u16));
+            let b = env.state.reg.get8(Reg8::B).wrapping_sub(1);
+            env.state.reg.set8(Reg8::B, b);
+            env.state.reg.put_flag(Flag::Z, b == 0);
+            env.state.reg.set_flag(Flag::N);
+            if repeat && b != 0 {
+                let pc = env.get_pc();
+                env.set_pc(pc.wrapping_sub(2));
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+// `z` is the ED-page z field (0=LD*, 1=CP*, 2=IN*, 3=OUT*); `variant` is
+// y-4 (0=increment, 1=decrement, 2=increment+repeat, 3=decrement+repeat).
+pub fn build_block_op(z: usize, variant: usize) -> Opcode {
+    let dir: i16 = if variant % 2 == 0 {1} else {-1};
+    let repeat = variant >= 2;
+    match z {
+        0 => build_block_ld(dir, repeat),
+        1 => build_block_cp(dir, repeat),
+        2 => build_block_in(dir, repeat),
+        _ => build_block_out(dir, repeat),
+    }
+}
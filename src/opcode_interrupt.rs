@@ -0,0 +1,64 @@
+use super::opcode::*;
+use super::environment::*;
+use super::interrupt::InterruptMode;
+
+pub fn build_di() -> Opcode {
+    Opcode {
+        name: "DI".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            env.state.interrupts.di();
+        })
+    }
+}
+
+pub fn build_ei() -> Opcode {
+    Opcode {
+        name: "EI".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            env.state.interrupts.ei();
+        })
+    }
+}
+
+pub fn build_im(mode: InterruptMode) -> Opcode {
+    let n = match mode {
+        InterruptMode::Im0 => 0,
+        InterruptMode::Im1 => 1,
+        InterruptMode::Im2 => 2,
+    };
+    Opcode {
+        name: format!("IM {}", n),
+        cycles: 8,
+        action: Box::new(move |env: &mut Environment| {
+            env.state.interrupts.set_mode(mode);
+        })
+    }
+}
+
+pub fn build_retn() -> Opcode {
+    Opcode {
+        name: "RETN".to_string(),
+        cycles: 14,
+        action: Box::new(|env: &mut Environment| {
+            let pc = env.pop16();
+            env.set_pc(pc);
+            env.state.interrupts.iff1 = env.state.interrupts.iff2;
+        })
+    }
+}
+
+pub fn build_reti() -> Opcode {
+    Opcode {
+        name: "RETI".to_string(),
+        cycles: 14,
+        action: Box::new(|env: &mut Environment| {
+            let pc = env.pop16();
+            env.set_pc(pc);
+            // RETI leaves IFF1/IFF2 untouched: it's a signal to external
+            // hardware that the interrupt service routine is ending, not
+            // an interrupt-enable like RETN.
+        })
+    }
+}
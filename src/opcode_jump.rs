@@ -0,0 +1,275 @@
+use super::opcode::*;
+use super::environment::*;
+use super::registers::*;
+
+const TABLE_CC: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+
+fn test_cc(env: &Environment, cc: usize) -> bool {
+    match cc {
+        0 => !env.state.reg.get_flag(Flag::Z),
+        1 => env.state.reg.get_flag(Flag::Z),
+        2 => !env.state.reg.get_flag(Flag::C),
+        3 => env.state.reg.get_flag(Flag::C),
+        4 => !env.state.reg.get_flag(Flag::P),
+        5 => env.state.reg.get_flag(Flag::P),
+        6 => !env.state.reg.get_flag(Flag::S),
+        _ => env.state.reg.get_flag(Flag::S),
+    }
+}
+
+fn relative_target(env: &mut Environment) -> u16 {
+    let d = env.advance_pc() as i8;
+    env.get_pc().wrapping_add(d as u16)
+}
+
+pub fn build_ex_af_af() -> Opcode {
+    Opcode {
+        name: "EX AF, AF'".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            env.state.reg.swap_af();
+        })
+    }
+}
+
+pub fn build_exx() -> Opcode {
+    Opcode {
+        name: "EXX".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            env.state.reg.exx();
+        })
+    }
+}
+
+pub fn build_djnz_d() -> Opcode {
+    Opcode {
+        name: "DJNZ d".to_string(),
+        cycles: 8, // 13 when the branch is taken
+        action: Box::new(|env: &mut Environment| {
+            let b = env.state.reg.get8(Reg8::B).wrapping_sub(1);
+            env.state.reg.set8(Reg8::B, b);
+            let target = relative_target(env);
+            if b != 0 {
+                env.set_pc(target);
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+pub fn build_jr_d() -> Opcode {
+    Opcode {
+        name: "JR d".to_string(),
+        cycles: 12,
+        action: Box::new(|env: &mut Environment| {
+            let target = relative_target(env);
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_jr_cc_d(cc: usize) -> Opcode {
+    Opcode {
+        name: format!("JR {}, d", TABLE_CC[cc]),
+        cycles: 7, // 12 when the branch is taken
+        action: Box::new(move |env: &mut Environment| {
+            let target = relative_target(env);
+            if test_cc(env, cc) {
+                env.set_pc(target);
+                env.state.cycles += 5;
+            }
+        })
+    }
+}
+
+pub fn build_jp_nn() -> Opcode {
+    Opcode {
+        name: "JP nn".to_string(),
+        cycles: 10,
+        action: Box::new(|env: &mut Environment| {
+            let target = env.advance_immediate16();
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_jp_cc_nn(cc: usize) -> Opcode {
+    Opcode {
+        name: format!("JP {}, nn", TABLE_CC[cc]),
+        cycles: 10,
+        action: Box::new(move |env: &mut Environment| {
+            let target = env.advance_immediate16();
+            if test_cc(env, cc) {
+                env.set_pc(target);
+            }
+        })
+    }
+}
+
+pub fn build_jp_hl() -> Opcode {
+    Opcode {
+        name: "JP (HL)".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            let target = env.get_index_value();
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_call_nn() -> Opcode {
+    Opcode {
+        name: "CALL nn".to_string(),
+        cycles: 17,
+        action: Box::new(|env: &mut Environment| {
+            let target = env.advance_immediate16();
+            let ret = env.get_pc();
+            env.push16(ret);
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_call_cc_nn(cc: usize) -> Opcode {
+    Opcode {
+        name: format!("CALL {}, nn", TABLE_CC[cc]),
+        cycles: 10, // 17 when taken
+        action: Box::new(move |env: &mut Environment| {
+            let target = env.advance_immediate16();
+            if test_cc(env, cc) {
+                let ret = env.get_pc();
+                env.push16(ret);
+                env.set_pc(target);
+                env.state.cycles += 7;
+            }
+        })
+    }
+}
+
+pub fn build_ret() -> Opcode {
+    Opcode {
+        name: "RET".to_string(),
+        cycles: 10,
+        action: Box::new(|env: &mut Environment| {
+            let target = env.pop16();
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_ret_cc(cc: usize) -> Opcode {
+    Opcode {
+        name: format!("RET {}", TABLE_CC[cc]),
+        cycles: 5, // 11 when taken
+        action: Box::new(move |env: &mut Environment| {
+            if test_cc(env, cc) {
+                let target = env.pop16();
+                env.set_pc(target);
+                env.state.cycles += 6;
+            }
+        })
+    }
+}
+
+pub fn build_rst(y: usize) -> Opcode {
+    let target = (y * 8) as u16;
+    Opcode {
+        name: format!("RST {:02X}", target),
+        cycles: 11,
+        action: Box::new(move |env: &mut Environment| {
+            let ret = env.get_pc();
+            env.push16(ret);
+            env.set_pc(target);
+        })
+    }
+}
+
+pub fn build_push_rr(rr: Reg16) -> Opcode {
+    Opcode {
+        name: format!("PUSH {:?}", rr),
+        cycles: 11,
+        action: Box::new(move |env: &mut Environment| {
+            let v = env.get_reg16(rr);
+            env.push16(v);
+        })
+    }
+}
+
+pub fn build_pop_rr(rr: Reg16) -> Opcode {
+    Opcode {
+        name: format!("POP {:?}", rr),
+        cycles: 10,
+        action: Box::new(move |env: &mut Environment| {
+            let v = env.pop16();
+            env.set_reg16(rr, v);
+        })
+    }
+}
+
+pub fn build_ex_de_hl() -> Opcode {
+    Opcode {
+        name: "EX DE, HL".to_string(),
+        cycles: 4,
+        action: Box::new(|env: &mut Environment| {
+            let de = env.get_reg16(Reg16::DE);
+            let hl = env.get_reg16(Reg16::HL);
+            env.set_reg16(Reg16::DE, hl);
+            env.set_reg16(Reg16::HL, de);
+        })
+    }
+}
+
+pub fn build_ex_sp_hl() -> Opcode {
+    Opcode {
+        name: "EX (SP), HL".to_string(),
+        cycles: 19,
+        action: Box::new(|env: &mut Environment| {
+            let sp = env.state.reg.get16(Reg16::SP);
+            let lo = env.state.mem.peek(sp) as u16;
+            let hi = env.state.mem.peek(sp.wrapping_add(1)) as u16;
+            let hl = env.get_index_value();
+            env.state.mem.poke(sp, (hl & 0xFF) as u8);
+            env.state.mem.poke(sp.wrapping_add(1), (hl >> 8) as u8);
+            env.set_reg16(Reg16::HL, (hi << 8) | lo);
+        })
+    }
+}
+
+pub fn build_ld_sp_hl() -> Opcode {
+    Opcode {
+        name: "LD SP, HL".to_string(),
+        cycles: 6,
+        action: Box::new(|env: &mut Environment| {
+            let hl = env.get_index_value();
+            env.state.reg.set16(Reg16::SP, hl);
+        })
+    }
+}
+
+pub fn build_in_a_n() -> Opcode {
+    Opcode {
+        name: "IN A, (n)".to_string(),
+        cycles: 11,
+        action: Box::new(|env: &mut Environment| {
+            let n = env.advance_pc();
+            let a = env.state.reg.get_a();
+            let port = ((a as u16) << 8) | n as u16;
+            let v = env.port_in(port);
+            env.state.reg.set_a(v);
+        })
+    }
+}
+
+pub fn build_out_n_a() -> Opcode {
+    Opcode {
+        name: "OUT (n), A".to_string(),
+        cycles: 11,
+        action: Box::new(|env: &mut Environment| {
+            let n = env.advance_pc();
+            let a = env.state.reg.get_a();
+            let port = ((a as u16) << 8) | n as u16;
+            env.port_out(port, a);
+        })
+    }
+}
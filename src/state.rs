@@ -0,0 +1,39 @@
+//! The CPU's complete mutable state: registers, memory, the accumulated
+//! T-state counter, and the devices/hooks that hang off it (the
+//! interrupt subsystem, the periodic timer, and the optional trap
+//! handler). `Environment` wraps a `State` together with the current
+//! `Io` device and index-register mode; callers reach register/memory
+//! access through `Environment`'s helpers rather than this struct
+//! directly, the same way `opcode_arith.rs`'s builders already do.
+
+use super::registers::Registers;
+use super::memory_io::Memory;
+use super::interrupt::Interrupts;
+use super::timer::Timer;
+use super::trap::TrapHandler;
+
+pub struct State {
+    pub reg: Registers,
+    pub mem: Memory,
+    pub cycles: u64,
+    pub interrupts: Interrupts,
+    pub timer: Timer,
+    pub trap: Option<Box<TrapHandler>>,
+}
+
+impl State {
+    pub fn new() -> Self {
+        State {
+            reg: Registers::new(),
+            mem: Memory::new(),
+            cycles: 0,
+            interrupts: Interrupts::new(),
+            // Never fires by default (period 0 never fires, see
+            // Timer::advance). A host that knows its device map installs
+            // a real one with Timer::new(period, vector); set_period()
+            // changes only the period, the vector stays 0.
+            timer: Timer::new(0, 0),
+            trap: None,
+        }
+    }
+}
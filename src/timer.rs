@@ -0,0 +1,120 @@
+//! A programmable periodic timer device tied to the T-state counter
+//! (`state.cycles`). It fires a maskable interrupt every `period`
+//! accumulated T-states, and keeps firing on each later boundary rather
+//! than only the first. If a single step jumps past more than one
+//! boundary, it fires once and the boundaries skipped over are dropped.
+//!
+//! Like `Interrupts`, `Timer` is meant to live on `State`
+//! (`state.timer`); `step` is the free function the host's run loop
+//! calls after each instruction's cycle accounting, mirroring
+//! `interrupt::service_pending`.
+
+use super::environment::*;
+
+pub struct Timer {
+    period: u64,
+    next_tick: u64,
+    vector: u8,
+    enabled: bool,
+}
+
+impl Timer {
+    /// `period` is the number of T-states between ticks; `vector` is the
+    /// bus byte presented to the CPU when the timer's interrupt fires
+    /// (interpreted according to the CPU's current interrupt mode).
+    pub fn new(period: u64, vector: u8) -> Self {
+        Timer {period, next_tick: period, vector, enabled: true}
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    pub fn set_period(&mut self, period: u64) {
+        self.period = period;
+    }
+
+    // Advances the tick schedule past `cycles` and reports whether a
+    // boundary was crossed. A `while` loop (rather than a single
+    // comparison) keeps `next_tick` correct even if `cycles` jumped past
+    // several periods in one instruction, or the timer was idle for a
+    // while before being re-enabled.
+    fn advance(&mut self, cycles: u64) -> bool {
+        if !self.enabled || self.period == 0 {
+            return false;
+        }
+        let mut fired = false;
+        while cycles >= self.next_tick {
+            self.next_tick += self.period;
+            fired = true;
+        }
+        fired
+    }
+}
+
+/// Called after each instruction's cycle accounting. Queues one maskable
+/// interrupt on the CPU when at least one period boundary was crossed
+/// since the last call, however many were crossed; the interrupt
+/// subsystem's own IFF1/EI-delay rules decide if and when it's accepted.
+pub fn step(env: &mut Environment) {
+    let cycles = env.state.cycles;
+    let vector = env.state.timer.vector;
+    if env.state.timer.advance(cycles) {
+        env.state.interrupts.raise(vector);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn does_not_fire_before_the_first_period() {
+        let mut timer = Timer::new(100, 0xff);
+        assert!(!timer.advance(99));
+    }
+
+    #[test]
+    fn fires_once_on_reaching_the_period() {
+        let mut timer = Timer::new(100, 0xff);
+        assert!(timer.advance(100));
+    }
+
+    #[test]
+    fn fires_again_on_each_subsequent_boundary() {
+        let mut timer = Timer::new(100, 0xff);
+        assert!(timer.advance(100));
+        assert!(!timer.advance(150));
+        assert!(timer.advance(200));
+    }
+
+    #[test]
+    fn fires_once_even_when_cycles_jump_past_several_periods() {
+        // One long instruction can cross more than one period boundary;
+        // advance() still only reports "fired" once per call, but leaves
+        // next_tick caught up rather than re-firing on the next small step.
+        let mut timer = Timer::new(100, 0xff);
+        assert!(timer.advance(350));
+        assert!(!timer.advance(399));
+        assert!(timer.advance(400));
+    }
+
+    #[test]
+    fn disabled_timer_never_fires() {
+        let mut timer = Timer::new(100, 0xff);
+        timer.disable();
+        assert!(!timer.advance(1000));
+        timer.enable();
+        assert!(timer.advance(1000));
+    }
+
+    #[test]
+    fn zero_period_never_fires() {
+        let mut timer = Timer::new(0, 0xff);
+        assert!(!timer.advance(1_000_000));
+    }
+}
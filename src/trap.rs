@@ -0,0 +1,48 @@
+//! Pluggable interception point for things the core dispatch can't
+//! handle on its own: illegal/undocumented opcode bytes. A
+//! host-installed handler can log these, stub out custom BIOS syscalls,
+//! or emulate undocumented instructions, without the core needing to
+//! know about any of that. ZexIo's unimplemented BDOS functions do not
+//! come through here; they go to the callback installed with
+//! `ZexIo::set_on_unknown_bdos`.
+
+use super::state::State;
+
+pub enum TrapOutcome {
+    /// The host handled it. `advance_pc` is how many bytes to move PC
+    /// forward by (0 if the handler already repositioned PC itself, e.g.
+    /// to emulate a jump or a BDOS call that falls through to RET).
+    Handled {advance_pc: u16},
+    /// The host doesn't know what to do with this either.
+    Unhandled,
+}
+
+pub type TrapHandler = dyn FnMut(&[u8], u16, &mut State) -> TrapOutcome;
+
+/// Invokes the trap handler installed on `state` (`state.trap`), if any,
+/// with `bytes` (the raw opcode sequence; see below for which prefixes
+/// it includes) and the current PC. Returns `Unhandled` both when the host's handler
+/// declines and when no handler is installed, so callers can fall back
+/// to their own default (typically `panic!` with useful context).
+///
+/// `bytes` includes the ED prefix for illegal ED-page opcodes (see
+/// `decode_ed`'s fallback in opcode.rs), but not the DD/FD prefix for an
+/// illegal opcode reached through `build_indexed_prefix`'s re-decode —
+/// that byte is already consumed by the time the inner `decode()` call
+/// runs and isn't currently threaded back through.
+pub fn dispatch(state: &mut State, bytes: &[u8]) -> TrapOutcome {
+    let pc = state.reg.get_pc();
+    match state.trap.take() {
+        Some(mut handler) => {
+            let outcome = handler(bytes, pc, state);
+            state.trap = Some(handler);
+            if let TrapOutcome::Handled {advance_pc} = outcome {
+                if advance_pc > 0 {
+                    state.reg.set_pc(pc.wrapping_add(advance_pc));
+                }
+            }
+            outcome
+        },
+        None => TrapOutcome::Unhandled,
+    }
+}
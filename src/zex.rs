@@ -0,0 +1,110 @@
+//! CRC-capture runner for the standard ZEXDOC/ZEXALL Z80 instruction
+//! exercisers — not yet the automated pass/fail correctness suite the
+//! design aims at, since `KNOWN_GOOD` (below) has no verified reference
+//! values in it yet.
+//!
+//! Loads the exerciser .com image at 0x0100 (its standard CP/M load
+//! address), points the warm-boot vector at 0x0000 so the run loop knows
+//! when the program has finished, and drives the CPU through ZexIo.
+//! Each exerciser prints one line per instruction group ending in
+//! "CRC is XXXXXXXX"; this module captures that and, once `KNOWN_GOOD`
+//! is populated from a trusted reference run, will check it against the
+//! known-good values to turn the BDOS scaffolding in zexio.rs into a
+//! real correctness suite. Until then, every group reports `??` rather
+//! than a false PASS or FAIL.
+
+use super::environment::Environment;
+use super::opcode;
+use super::zexio::ZexIo;
+
+const LOAD_ADDRESS: u16 = 0x0100;
+const WARM_BOOT: u16 = 0x0000;
+
+pub struct GroupResult {
+    pub name: String,
+    pub crc: u32,
+    pub expected: Option<u32>,
+}
+
+impl GroupResult {
+    pub fn passed(&self) -> bool {
+        self.expected == Some(self.crc)
+    }
+}
+
+// CRC32 values the upstream zexdoc/zexall exercisers report per
+// instruction group when run against a correctly emulated Z80 (see the
+// "CRC is" lines in zexall's own output, checked against a reference
+// run). A group absent from this table is still captured and reported,
+// just without a pass/fail verdict — extending coverage only ever means
+// adding a row here, not touching the runner.
+//
+// Empty for now: populating it requires copying the real per-group
+// values out of a trusted zexdoc/zexall reference run (or the upstream
+// source's own comments), and none is available in this tree to copy
+// from. Inventing plausible-looking hex here would be worse than
+// leaving it empty — it would silently "pass" every group. Until a
+// verified reference run is on hand, every group reports `??` via
+// `GroupResult::passed()`/`report()` rather than a false PASS or FAIL.
+const KNOWN_GOOD: &[(&str, u32)] = &[
+];
+
+/// Loads `program` (the bytes of zexdoc.com or zexall.com) into `env`
+/// and runs it to completion, returning one `GroupResult` per group it
+/// reports.
+pub fn run(env: &mut Environment, program: &[u8]) -> Vec<GroupResult> {
+    for (offset, &byte) in program.iter().enumerate() {
+        env.state.mem.poke(LOAD_ADDRESS.wrapping_add(offset as u16), byte);
+    }
+    env.set_pc(LOAD_ADDRESS);
+
+    let (io, output) = ZexIo::new();
+    env.set_io(Box::new(io));
+
+    while env.get_pc() != WARM_BOOT {
+        let code = env.advance_pc();
+        opcode::decode(code).execute(env);
+        env.state.interrupts.end_instruction();
+        super::timer::step(env);
+        super::interrupt::service_pending(env);
+    }
+
+    parse_results(&output.borrow())
+}
+
+fn parse_results(output: &str) -> Vec<GroupResult> {
+    const MARKER: &str = "CRC is ";
+    let mut results = Vec::new();
+    for line in output.lines() {
+        let Some(pos) = line.find(MARKER) else { continue; };
+        let name = line[..pos].trim().to_string();
+        let hex = line[pos + MARKER.len()..].trim().trim_start_matches("0x");
+        let Ok(crc) = u32::from_str_radix(hex, 16) else { continue; };
+        let expected = KNOWN_GOOD.iter().find(|(n, _)| *n == name).map(|(_, c)| *c);
+        results.push(GroupResult {name, crc, expected});
+    }
+    results
+}
+
+/// Prints a pass/fail/unknown line per group plus a final tally, the way
+/// a CI log for this suite would read.
+pub fn report(results: &[GroupResult]) {
+    let (mut passed, mut failed, mut unknown) = (0, 0, 0);
+    for result in results {
+        match result.expected {
+            Some(expected) if expected == result.crc => {
+                passed += 1;
+                println!("PASS {} (CRC {:08X})", result.name, result.crc);
+            },
+            Some(expected) => {
+                failed += 1;
+                println!("FAIL {} (CRC {:08X}, expected {:08X})", result.name, result.crc, expected);
+            },
+            None => {
+                unknown += 1;
+                println!("??   {} (CRC {:08X}, no reference value on file)", result.name, result.crc);
+            },
+        }
+    }
+    println!("{} passed, {} failed, {} unknown", passed, failed, unknown);
+}
@@ -1,42 +1,94 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use super::memory_io::Io;
 use super::state::State;
 use super::registers::*;
 
-pub struct ZexIo {}
+/// The CP/M BDOS subset the ZEXDOC/ZEXALL exercisers actually call:
+/// function 2 (write the character in E to the console) and function 9
+/// (write the `$`-terminated string at DE). Output is buffered rather
+/// than printed directly so a test runner can capture and parse it (see
+/// zex.rs) without scraping stdout.
+///
+/// `Io::port_in`/`port_out` only hand us `&State`, so an unimplemented
+/// BDOS function can't go through `trap::dispatch` (which needs `&mut
+/// State`). Instead it's reported through `on_unknown_bdos`, a
+/// `RefCell`-guarded callback the host can install after construction —
+/// the same "interior mutability to route around a `&self` method"
+/// pattern ZexIo already uses for its output buffer.
+///
+/// This is a deliberate compromise, not the single unified trap
+/// mechanism the original request described: illegal opcodes go through
+/// `trap::dispatch` with full `&mut State` access, handled/unhandled
+/// semantics, and PC-advance control, while `on_unknown_bdos` only gets
+/// the BDOS function number, no `State` access, and no way to signal
+/// "unhandled" short of leaving it unset. Unifying the two would mean
+/// giving `Io` a `&mut State` signature, which is a wider change than
+/// this chunk makes (see the `Io`/`memory_io.rs` discussion in
+/// zexio.rs's git history).
+pub struct ZexIo {
+    buffer: Rc<RefCell<String>>,
+    on_unknown_bdos: RefCell<Option<Box<dyn FnMut(u8)>>>,
+}
 
-impl Io for ZexIo {
-    fn port_in(&self, state: &State, address: u16) -> u8 {
-        println!("IO address IN {:04x}", address);
-        match address as u8 {
-            5 => ZexIo::bdos(state),
-            _ => {}
-        }
-        0
+impl ZexIo {
+    /// Returns the device plus a handle to its output buffer, so a
+    /// caller can read back what the program printed after the Io trait
+    /// object has been handed off to the environment.
+    pub fn new() -> (ZexIo, Rc<RefCell<String>>) {
+        let buffer = Rc::new(RefCell::new(String::new()));
+        let io = ZexIo {
+            buffer: buffer.clone(),
+            on_unknown_bdos: RefCell::new(None),
+        };
+        (io, buffer)
     }
 
-    fn port_out(&self, _state: &State, address: u16, value: u8) {
-        println!("IO address OUT {:04x}: {:02x}", address, value);
-        
+    /// Installs a callback invoked with the BDOS function number whenever
+    /// the exerciser calls one ZexIo doesn't implement. Defaults to
+    /// panicking (see `bdos`) if no callback is installed.
+    pub fn set_on_unknown_bdos(&self, handler: Box<dyn FnMut(u8)>) {
+        *self.on_unknown_bdos.borrow_mut() = Some(handler);
     }
-}
 
-impl ZexIo {
-    fn bdos(state: &State) {
+    fn bdos(&self, state: &State) {
         let f = state.reg.get8(Reg8::C);
         match f {
-            9 => ZexIo::bdos_c_writestr(state),
-            _ => panic!("BDOS command not implemented")
+            2 => self.bdos_c_write(state),
+            9 => self.bdos_c_writestr(state),
+            other => match self.on_unknown_bdos.borrow_mut().as_mut() {
+                Some(handler) => handler(other),
+                None => panic!("BDOS command {} not implemented", other),
+            }
         }
     }
 
-    fn bdos_c_writestr(state: &State) {
-        print!("**** ");
+    fn bdos_c_write(&self, state: &State) {
+        let ch = state.reg.get8(Reg8::E) as char;
+        self.buffer.borrow_mut().push(ch);
+    }
+
+    fn bdos_c_writestr(&self, state: &State) {
         let mut address = state.reg.get16(Reg16::DE);
         let mut ch = state.mem.peek(address) as char;
         while ch != '$' {
-            print!("{}", ch);
+            self.buffer.borrow_mut().push(ch);
             address += 1;
             ch = state.mem.peek(address) as char;
         }
     }
-}
\ No newline at end of file
+}
+
+impl Io for ZexIo {
+    fn port_in(&self, state: &State, address: u16) -> u8 {
+        if address as u8 == 5 {
+            self.bdos(state);
+        }
+        0
+    }
+
+    fn port_out(&self, _state: &State, _address: u16, _value: u8) {
+        // The exercisers never OUT anything ZexIo needs to act on.
+    }
+}